@@ -0,0 +1,25 @@
+//! Compares the `serde_json` and `simd-json` backends for parsing
+//! `smartctl -x --json` output, run separately against each
+//! `EXAMPLE_ALL` fixture:
+//!
+//!     cargo bench --bench json_parsing
+//!     cargo bench --bench json_parsing --features simd-json
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use smartctl_wrapper::json_backend::parse_smartctl_json;
+use smartctl_wrapper::test_util::example_outputs::EXAMPLE_ALL;
+
+fn bench_parse_smartctl_json(c: &mut Criterion) {
+    for (i, example) in EXAMPLE_ALL.iter().enumerate() {
+        c.bench_function(&format!("parse_smartctl_json[{}]", i), |b| {
+            b.iter_batched(
+                || example.as_bytes().to_vec(),
+                |mut bytes| parse_smartctl_json(&mut bytes).unwrap(),
+                criterion::BatchSize::SmallInput,
+            )
+        });
+    }
+}
+
+criterion_group!(benches, bench_parse_smartctl_json);
+criterion_main!(benches);
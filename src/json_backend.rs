@@ -0,0 +1,41 @@
+//! Backend-agnostic JSON parsing used throughout the self-test parsing
+//! code.
+//!
+//! Parsing `smartctl -x --json` output is the dominant cost when scanning
+//! dozens of drives, so the initial byte-slice-to-value step is pluggable:
+//! by default it goes through `serde_json`, while the `simd-json` feature
+//! swaps in SIMD-accelerated parsing for hosts that care more about
+//! throughput than `simd-json`'s in-place-buffer-mutation tradeoff.
+
+use anyhow::Error;
+
+/// Parses a `smartctl --json` buffer into a JSON value, using whichever
+/// backend is enabled for this build.
+///
+/// `bytes` is taken as `&mut` because the `simd-json` backend mutates the
+/// buffer in place while building its tape; the `serde_json` backend
+/// ignores the mutability but keeps the same signature so the rest of the
+/// self-test parsing code stays backend-agnostic.
+#[cfg(not(feature = "simd-json"))]
+pub fn parse_smartctl_json(bytes: &mut [u8]) -> Result<serde_json::Value, Error> {
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+/// Parses a `smartctl --json` buffer into a JSON value, using whichever
+/// backend is enabled for this build.
+///
+/// `bytes` is taken as `&mut` because the `simd-json` backend mutates the
+/// buffer in place while building its tape; the `serde_json` backend
+/// ignores the mutability but keeps the same signature so the rest of the
+/// self-test parsing code stays backend-agnostic.
+///
+/// The `simd-json` tape is converted into a `serde_json::Value` before
+/// returning, so `parse_json_ata_smart_data_to_self_test` and
+/// `parse_json_self_test_log` don't need to know which backend produced
+/// it. The SIMD win is still real: it's paid once here, up front, instead
+/// of throughout every downstream `.get()`/`.as_*()` call.
+#[cfg(feature = "simd-json")]
+pub fn parse_smartctl_json(bytes: &mut [u8]) -> Result<serde_json::Value, Error> {
+    let tape = simd_json::to_owned_value(bytes)?;
+    Ok(serde_json::to_value(tape)?)
+}
@@ -0,0 +1,227 @@
+//! Starting and aborting self-tests, complementing the read-only status
+//! parsing in [`smartctl_test`](super::smartctl_test).
+
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::Error;
+
+use super::smartctl_test::SmartCtlSelfTest;
+
+/// The kind of self-test to start, mirroring smartctl's `-t` argument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelfTestKind {
+    Short,
+    Long,
+    Conveyance,
+    /// A selective self-test restricted to the given LBA span(s), e.g.
+    /// `"0-1000"` or `"0-1000,2000-3000"` — smartctl's `-t select` requires
+    /// at least one span, so it is carried here rather than being a
+    /// unit variant.
+    Select(String),
+}
+
+impl SelfTestKind {
+    fn as_smartctl_arg(&self) -> String {
+        match self {
+            SelfTestKind::Short => "short".to_string(),
+            SelfTestKind::Long => "long".to_string(),
+            SelfTestKind::Conveyance => "conveyance".to_string(),
+            SelfTestKind::Select(span) => format!("select,{}", span),
+        }
+    }
+
+    /// The key this test type is filed under in smartctl's
+    /// `polling_minutes` object. Selective tests have no entry of their
+    /// own there and use the `"short"` entry.
+    fn polling_minutes_key(&self) -> &'static str {
+        match self {
+            SelfTestKind::Short => "short",
+            SelfTestKind::Long => "extended",
+            SelfTestKind::Conveyance => "conveyance",
+            SelfTestKind::Select(_) => "short",
+        }
+    }
+}
+
+/// Errors specific to starting or aborting a self-test.
+#[derive(Debug)]
+pub enum SelfTestControlError {
+    /// `start_test` was called while `is_running()` already reported a
+    /// test in progress on the device.
+    AlreadyRunning,
+    /// smartctl exited with a non-zero status; `stderr` is its captured
+    /// error output.
+    CommandFailed { stderr: String },
+    Other(Error),
+}
+
+impl std::fmt::Display for SelfTestControlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelfTestControlError::AlreadyRunning => {
+                write!(f, "a self-test is already running on this device")
+            }
+            SelfTestControlError::CommandFailed { stderr } => {
+                write!(f, "smartctl failed: {}", stderr.trim())
+            }
+            SelfTestControlError::Other(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for SelfTestControlError {}
+
+impl From<Error> for SelfTestControlError {
+    fn from(err: Error) -> Self {
+        SelfTestControlError::Other(err)
+    }
+}
+
+/// Starts `test_kind` on `device` via `smartctl -t`, returning the
+/// estimated completion time.
+///
+/// Rejects with [`SelfTestControlError::AlreadyRunning`] if
+/// `current_status` (typically a recent [`SmartCtlSelfTest`] for the same
+/// device) reports a test already in progress.
+pub fn start_test(
+    device: &str,
+    test_kind: &SelfTestKind,
+    current_status: &SmartCtlSelfTest,
+) -> Result<Duration, SelfTestControlError> {
+    if current_status.is_running() {
+        return Err(SelfTestControlError::AlreadyRunning);
+    }
+
+    let output = Command::new("smartctl")
+        .args(["-t", &test_kind.as_smartctl_arg(), device])
+        .output()
+        .map_err(Error::from)?;
+
+    if !output.status.success() {
+        return Err(SelfTestControlError::CommandFailed {
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    Ok(estimate_completion_time(&stdout, test_kind, current_status)?)
+}
+
+/// Aborts whatever self-test is currently running on `device`, via
+/// `smartctl -X`.
+pub fn abort_test(device: &str) -> Result<(), SelfTestControlError> {
+    let output = Command::new("smartctl")
+        .args(["-X", device])
+        .output()
+        .map_err(Error::from)?;
+
+    if !output.status.success() {
+        return Err(SelfTestControlError::CommandFailed {
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Parses smartctl's `-t` output for a line like:
+///
+/// ```text
+/// Please wait 2 minutes for test to complete.
+/// ```
+fn parse_completion_time(stdout: &str) -> Option<Duration> {
+    let line = stdout.lines().find(|line| line.contains("Please wait"))?;
+    let minutes: u64 = line.split_whitespace().find_map(|word| word.parse().ok())?;
+
+    Some(Duration::from_secs(minutes * 60))
+}
+
+/// The drive's own `polling_minutes` hint for `test_kind`.
+fn estimated_from_polling(
+    test_kind: &SelfTestKind,
+    current_status: &SmartCtlSelfTest,
+) -> Option<Duration> {
+    let minutes = current_status
+        .get_test_types()
+        .ok()?
+        .into_iter()
+        .find(|(name, _)| name == test_kind.polling_minutes_key())
+        .map(|(_, minutes)| minutes)?;
+
+    Some(Duration::from_secs(minutes * 60))
+}
+
+/// How far the completion time smartctl printed is allowed to diverge
+/// from the drive's own `polling_minutes` hint (as a multiple, in either
+/// direction) before it's treated as a mis-parse rather than trusted.
+const POLLING_CROSS_CHECK_RATIO: f64 = 10.0;
+
+/// Combines the completion time parsed from `smartctl -t`'s stdout with
+/// the drive's own `polling_minutes` hint for `test_kind`, cross-checking
+/// the two against each other when both are available: the printed value
+/// is used unless it disagrees wildly with `polling_minutes`, in which
+/// case `polling_minutes` — which comes straight from the drive rather
+/// than from parsing human-readable text — wins.
+fn estimate_completion_time(
+    stdout: &str,
+    test_kind: &SelfTestKind,
+    current_status: &SmartCtlSelfTest,
+) -> Result<Duration, Error> {
+    let parsed = parse_completion_time(stdout);
+    let from_polling = estimated_from_polling(test_kind, current_status);
+
+    match (parsed, from_polling) {
+        (Some(parsed), Some(from_polling)) => {
+            let ratio = parsed.as_secs_f64() / from_polling.as_secs_f64().max(1.0);
+            if (1.0 / POLLING_CROSS_CHECK_RATIO..POLLING_CROSS_CHECK_RATIO).contains(&ratio) {
+                Ok(parsed)
+            } else {
+                Ok(from_polling)
+            }
+        }
+        (Some(parsed), None) => Ok(parsed),
+        (None, Some(from_polling)) => Ok(from_polling),
+        (None, None) => Err(Error::msg("Could not determine estimated test completion time")),
+    }
+}
+
+#[cfg(feature = "async")]
+mod progress {
+    use anyhow::Error;
+
+    use super::{start_test, SelfTestKind};
+    use crate::smartctl_testing::smartctl_test::{
+        SmartCtlSelfTest, SmartCtlSelfTestProgress, SmartCtlSelfTestStatus,
+    };
+
+    /// Starts `test_kind` on `device` and awaits its completion, driving
+    /// [`SmartCtlSelfTestProgress`] to its terminal status.
+    ///
+    /// This is the `start_test(Short)?` + `await` combination the control
+    /// API and the progress stream are meant to be used together for.
+    pub async fn start_test_and_await(
+        device: &str,
+        test_kind: &SelfTestKind,
+        current_status: &SmartCtlSelfTest,
+    ) -> Result<SmartCtlSelfTestStatus, Error> {
+        start_test(device, test_kind, current_status)?;
+
+        let mut progress = SmartCtlSelfTestProgress::new(
+            device,
+            current_status,
+            test_kind.polling_minutes_key(),
+        )?;
+        let mut last = None;
+
+        while let Some(status) = progress.next().await {
+            last = Some(status?);
+        }
+
+        last.ok_or_else(|| Error::msg("smartctl reported no self-test status"))
+    }
+}
+
+#[cfg(feature = "async")]
+pub use progress::start_test_and_await;
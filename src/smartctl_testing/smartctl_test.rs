@@ -4,12 +4,104 @@
 use anyhow::Error;
 use serde::{self, Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// The meaning of a self-test's numeric status code, per smartctl's ATA
+/// self-test execution status byte.
+///
+/// Unlike matching on the raw `value`/`string` pair smartctl reports, this
+/// gives callers an exhaustive `match` instead of re-deriving the meaning of
+/// each magic number themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfTestState {
+    /// The most recent self-test completed without error.
+    Completed,
+    /// A self-test is in progress, with `remaining_percent` left to run.
+    InProgress { remaining_percent: u8 },
+    /// The self-test was interrupted by the host (e.g. a reset or power
+    /// cycle) rather than completing or being explicitly aborted.
+    Interrupted,
+    /// The self-test was aborted by a host command (smartctl's `-X`).
+    Aborted,
+    /// The self-test did not complete due to an unknown or fatal error.
+    FatalError,
+    /// A status code smartctl reported that this crate does not yet know
+    /// the meaning of. The original numeric value is preserved so callers
+    /// can still branch on it.
+    Unknown(u8),
+}
+
+impl SelfTestState {
+    fn from_value(value: u8, remaining_percent: Option<u8>) -> Self {
+        // The low nibble carries the percent-remaining for in-progress
+        // tests; the status itself lives in the high nibble.
+        match value >> 4 {
+            0x0 => SelfTestState::Completed,
+            0x1 => SelfTestState::Aborted,
+            0x2 => SelfTestState::Interrupted,
+            0x3..=0x8 => SelfTestState::FatalError,
+            0xf => SelfTestState::InProgress {
+                remaining_percent: remaining_percent.unwrap_or(0),
+            },
+            _ => SelfTestState::Unknown(value),
+        }
+    }
+
+    /// Whether this state represents a test that is still running.
+    pub fn is_running(&self) -> bool {
+        matches!(self, SelfTestState::InProgress { .. })
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct SmartCtlSelfTestStatus {
     value: u8,
     string: String,
     remaining_percent: Option<u8>,
     passed: Option<bool>,
+    state: SelfTestState,
+}
+
+/// The wire representation of [`SmartCtlSelfTestStatus`], used to drive
+/// [`SelfTestState`] selection from the raw `value` field while preserving
+/// `value`/`string` for round-tripping.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct RawSelfTestStatus {
+    value: u8,
+    string: String,
+    remaining_percent: Option<u8>,
+    passed: Option<bool>,
+}
+
+impl<'de> Deserialize<'de> for SmartCtlSelfTestStatus {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let raw = RawSelfTestStatus::deserialize(deserializer)?;
+        let state = SelfTestState::from_value(raw.value, raw.remaining_percent);
+
+        Ok(SmartCtlSelfTestStatus {
+            value: raw.value,
+            string: raw.string,
+            remaining_percent: raw.remaining_percent,
+            passed: raw.passed,
+            state,
+        })
+    }
+}
+
+impl Serialize for SmartCtlSelfTestStatus {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        RawSelfTestStatus {
+            value: self.value,
+            string: self.string.clone(),
+            remaining_percent: self.remaining_percent,
+            passed: self.passed,
+        }
+        .serialize(serializer)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -30,16 +122,18 @@ pub struct SmartCtlSelfTest {
 
 impl SmartCtlSelfTest {
     pub fn is_running(&self) -> bool {
-        self.status.value != 0
+        self.status.state.is_running()
     }
 
     pub fn get_test_types(&self) -> Result<Vec<(String, u64)>, Error> {
+        // `to_value(&self.polling_minutes)` already *is* the
+        // `{"short":…,"extended":…,"conveyance":…}` object, so there is no
+        // further "polling_minutes" key to descend into here.
         let test_types: Vec<(String, Result<u64, Error>)> =
             serde_json::to_value(&self.polling_minutes)?
-                .get("polling_minutes")
-                .and_then(|v| v.as_object())
-                .map(|v| v.into_iter())
-                .unwrap()
+                .as_object()
+                .ok_or_else(|| Error::msg("Expected polling_minutes to serialize to an object"))?
+                .into_iter()
                 .map(|(k, v)| {
                     (
                         k.clone(),
@@ -67,7 +161,130 @@ impl SmartCtlSelfTest {
     }
 }
 
-//TODO: Implement a test progress stream!
+/// How many times `SmartCtlSelfTestProgress` polls smartctl over the course
+/// of the test it is tracking, so a "short" test is sampled noticeably more
+/// often than an "extended" one without hammering smartctl.
+#[cfg(feature = "async")]
+const POLLS_PER_TEST: u64 = 10;
+
+#[cfg(feature = "async")]
+const DEFAULT_POLL_MINUTES: u64 = 2;
+
+/// Polls `smartctl` on a device and yields successive
+/// [`SmartCtlSelfTestStatus`] snapshots, suitable for driving a live
+/// progress bar. Available behind the `async` feature.
+///
+/// The stream terminates once a polled status's [`SelfTestState`] is no
+/// longer [`SelfTestState::InProgress`] or it carries a `passed` result,
+/// and the final yielded item always reflects that terminal state.
+#[cfg(feature = "async")]
+pub struct SmartCtlSelfTestProgress {
+    device: String,
+    poll_interval: std::time::Duration,
+    finished: bool,
+}
+
+#[cfg(feature = "async")]
+impl SmartCtlSelfTestProgress {
+    /// Begins tracking the self-test running on `device`, deriving the
+    /// polling interval from the `polling_minutes` entry for
+    /// `running_test_type` (smartctl's own key for the kind of test
+    /// actually in progress, e.g. `"short"` or `"extended"`) rather than
+    /// from whichever test type happens to be shortest.
+    pub fn new(
+        device: impl Into<String>,
+        self_test: &SmartCtlSelfTest,
+        running_test_type: &str,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            device: device.into(),
+            poll_interval: Self::poll_interval_for(self_test, running_test_type)?,
+            finished: false,
+        })
+    }
+
+    fn poll_interval_for(
+        self_test: &SmartCtlSelfTest,
+        running_test_type: &str,
+    ) -> Result<std::time::Duration, Error> {
+        let minutes = self_test
+            .get_test_types()?
+            .into_iter()
+            .find(|(test_type, _)| test_type == running_test_type)
+            .map(|(_, minutes)| minutes)
+            .unwrap_or(DEFAULT_POLL_MINUTES);
+
+        let poll_seconds = ((minutes * 60) / POLLS_PER_TEST).max(1);
+        Ok(std::time::Duration::from_secs(poll_seconds))
+    }
+
+    /// Runs `smartctl` once and returns the freshly parsed self-test
+    /// status, or `None` once the test has reached a terminal state.
+    ///
+    /// Errors are yielded rather than panicking; a polling error also ends
+    /// the stream, since there is no status left to recover from.
+    pub async fn next(&mut self) -> Option<Result<SmartCtlSelfTestStatus, Error>> {
+        if self.finished {
+            return None;
+        }
+
+        let status = match self.poll_once().await {
+            Ok(status) => status,
+            Err(err) => {
+                self.finished = true;
+                return Some(Err(err));
+            }
+        };
+
+        if !status.state.is_running() || status.passed.is_some() {
+            self.finished = true;
+        }
+
+        Some(Ok(status))
+    }
+
+    async fn poll_once(&self) -> Result<SmartCtlSelfTestStatus, Error> {
+        tokio::time::sleep(self.poll_interval).await;
+
+        let mut output = tokio::process::Command::new("smartctl")
+            .args(["-a", "--json", &self.device])
+            .output()
+            .await?;
+
+        let json = crate::json_backend::parse_smartctl_json(&mut output.stdout)?;
+        let ata_smart_data = json
+            .get("ata_smart_data")
+            .ok_or_else(|| Error::msg("Missing ata_smart_data field"))?;
+
+        Ok(parse_json_ata_smart_data_to_self_test(ata_smart_data)?.status)
+    }
+}
+
+/// Adapts [`SmartCtlSelfTestProgress`] into a [`futures_core::Stream`] for
+/// callers that want to `.await` progress in a `while let Some(...) = ...`
+/// loop or hand it to stream combinators, rather than calling `next()`
+/// directly.
+#[cfg(feature = "async")]
+pub fn progress_stream(
+    device: impl Into<String>,
+    self_test: &SmartCtlSelfTest,
+    running_test_type: &str,
+) -> Result<impl futures_core::Stream<Item = Result<SmartCtlSelfTestStatus, Error>>, Error> {
+    let mut progress = SmartCtlSelfTestProgress::new(device, self_test, running_test_type)?;
+
+    Ok(async_stream::stream! {
+        while let Some(status) = progress.next().await {
+            let is_terminal = status.is_err()
+                || matches!(&status, Ok(status) if !status.state.is_running() || status.passed.is_some());
+
+            yield status;
+
+            if is_terminal {
+                break;
+            }
+        }
+    })
+}
 
 pub fn parse_json_ata_smart_data_to_self_test(
     ata_smart_data: &serde_json::Value,
@@ -81,6 +298,106 @@ pub fn parse_json_ata_smart_data_to_self_test(
     Ok(SmartCtlSelfTest::deserialize(self_test)?)
 }
 
+/// The self-test type an [`AtaSelfTestLogEntry`] was run as (e.g. "Short
+/// offline" or "Extended offline"), as reported by smartctl.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SelfTestLogEntryType {
+    value: u8,
+    string: String,
+}
+
+/// A single historical row from `ata_smart_self_test_log`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AtaSelfTestLogEntry {
+    #[serde(rename = "type")]
+    test_type: SelfTestLogEntryType,
+    status: SmartCtlSelfTestStatus,
+    lifetime_hours: u32,
+    lba_of_first_error: Option<u64>,
+}
+
+/// A self-test's numeric result code from an NVMe self-test log row,
+/// e.g. "Completed without error" or "Aborted by a Set Features command".
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NvmeSelfTestResult {
+    value: u8,
+    string: String,
+}
+
+/// A single historical row from an NVMe self-test log table. NVMe rows
+/// use their own key names rather than ATA's `type`/`status`/
+/// `lifetime_hours`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NvmeSelfTestLogEntry {
+    self_test_code: SelfTestLogEntryType,
+    self_test_result: NvmeSelfTestResult,
+    power_on_hours: u64,
+    namespace_id_of_failing_lba: Option<u64>,
+    failing_lba: Option<u64>,
+}
+
+/// A single historical row from smartctl's self-test log
+/// (`ata_smart_self_test_log` for ATA drives, or the equivalent NVMe
+/// self-test log table), as opposed to [`SmartCtlSelfTest`] which only
+/// describes the currently running test.
+#[derive(Debug, Clone)]
+pub enum SelfTestLogEntry {
+    Ata(AtaSelfTestLogEntry),
+    Nvme(NvmeSelfTestLogEntry),
+}
+
+impl SelfTestLogEntry {
+    /// NVMe rows are keyed by `self_test_code` rather than ATA's `type`,
+    /// which is enough to tell the two schemas apart without guessing.
+    fn from_json(row: &serde_json::Value) -> Result<Self, Error> {
+        if row.get("self_test_code").is_some() {
+            Ok(SelfTestLogEntry::Nvme(NvmeSelfTestLogEntry::deserialize(
+                row,
+            )?))
+        } else {
+            Ok(SelfTestLogEntry::Ata(AtaSelfTestLogEntry::deserialize(
+                row,
+            )?))
+        }
+    }
+}
+
+/// A lazy, pull-based iterator over a self-test log table.
+///
+/// Rows are deserialized one at a time from the borrowed
+/// `serde_json::Value` array, so iterating a multi-year drive's worth of
+/// history never materializes the whole log as a `Vec` up front. Each
+/// item is its own `Result` so a single malformed row does not abort
+/// iteration of the rest of the log.
+pub struct SelfTestLogIter<'a> {
+    rows: std::slice::Iter<'a, serde_json::Value>,
+}
+
+impl<'a> Iterator for SelfTestLogIter<'a> {
+    type Item = Result<SelfTestLogEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.rows.next()?;
+        Some(SelfTestLogEntry::from_json(row))
+    }
+}
+
+/// Reads a self-test log table (the `table` array under
+/// `ata_smart_self_test_log.standard`, or an NVMe self-test log passed
+/// directly) and returns a [`SelfTestLogIter`] over its rows.
+pub fn parse_json_self_test_log(
+    self_test_log: &serde_json::Value,
+) -> Result<SelfTestLogIter<'_>, Error> {
+    let table = self_test_log
+        .get("standard")
+        .and_then(|v| v.get("table"))
+        .or_else(|| self_test_log.get("table"))
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| Error::msg("Missing self-test log table"))?;
+
+    Ok(SelfTestLogIter { rows: table.iter() })
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -161,4 +478,158 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_parse_json_self_test_log() {
+        let example_outputs = EXAMPLE_ALL_DURING_TESTING.iter().chain(EXAMPLE_ALL.iter());
+
+        for output in example_outputs {
+            let json: serde_json::Value = serde_json::from_str(output).unwrap();
+
+            let self_test_log = match json.get("ata_smart_self_test_log") {
+                Some(log) => log,
+                // Not every example output has ATA self-test log history.
+                None => continue,
+            };
+
+            let table_actual = self_test_log
+                .get("standard")
+                .and_then(|v| v.get("table"))
+                .and_then(|v| v.as_array())
+                .unwrap();
+
+            let entries: Vec<_> = parse_json_self_test_log(self_test_log)
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+
+            assert_eq!(entries.len(), table_actual.len());
+
+            for (entry, row_actual) in entries.iter().zip(table_actual.iter()) {
+                let entry = match entry {
+                    SelfTestLogEntry::Ata(entry) => entry,
+                    SelfTestLogEntry::Nvme(_) => panic!("expected an ATA self-test log entry"),
+                };
+
+                assert_eq!(
+                    entry.test_type.value,
+                    row_actual
+                        .get("type")
+                        .and_then(|v| v.get("value"))
+                        .and_then(|v| v.as_u64())
+                        .unwrap() as u8
+                );
+                assert_eq!(
+                    entry.test_type.string,
+                    row_actual
+                        .get("type")
+                        .and_then(|v| v.get("string"))
+                        .and_then(|v| v.as_str())
+                        .unwrap()
+                        .to_string()
+                );
+                assert_eq!(
+                    entry.status.value,
+                    row_actual
+                        .get("status")
+                        .and_then(|v| v.get("value"))
+                        .and_then(|v| v.as_u64())
+                        .unwrap() as u8
+                );
+                assert_eq!(
+                    entry.lifetime_hours,
+                    row_actual
+                        .get("lifetime_hours")
+                        .and_then(|v| v.as_u64())
+                        .unwrap() as u32
+                );
+                assert_eq!(
+                    entry.lba_of_first_error,
+                    row_actual.get("lba_of_first_error").and_then(|v| v.as_u64())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_json_self_test_log_nvme() {
+        let self_test_log = serde_json::json!({
+            "table": [
+                {
+                    "self_test_code": { "value": 1, "string": "Short" },
+                    "self_test_result": { "value": 0, "string": "Completed without error" },
+                    "power_on_hours": 456
+                },
+                {
+                    "self_test_code": { "value": 2, "string": "Extended" },
+                    "self_test_result": { "value": 7, "string": "Completed with error" },
+                    "power_on_hours": 123,
+                    "namespace_id_of_failing_lba": 1,
+                    "failing_lba": 99
+                }
+            ]
+        });
+
+        let entries: Vec<_> = parse_json_self_test_log(&self_test_log)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(entries.len(), 2);
+
+        match &entries[0] {
+            SelfTestLogEntry::Nvme(entry) => {
+                assert_eq!(entry.power_on_hours, 456);
+                assert_eq!(entry.self_test_result.value, 0);
+                assert_eq!(entry.failing_lba, None);
+            }
+            SelfTestLogEntry::Ata(_) => panic!("expected an NVMe self-test log entry"),
+        }
+
+        match &entries[1] {
+            SelfTestLogEntry::Nvme(entry) => {
+                assert_eq!(entry.power_on_hours, 123);
+                assert_eq!(entry.namespace_id_of_failing_lba, Some(1));
+                assert_eq!(entry.failing_lba, Some(99));
+            }
+            SelfTestLogEntry::Ata(_) => panic!("expected an NVMe self-test log entry"),
+        }
+    }
+
+    #[test]
+    fn test_parse_json_self_test_log_continues_past_malformed_rows() {
+        let self_test_log = serde_json::json!({
+            "standard": {
+                "table": [
+                    {
+                        "type": { "value": 1, "string": "Short offline" },
+                        "status": { "value": 0, "string": "Completed without error" },
+                        "lifetime_hours": 100
+                    },
+                    { "this": "is not a valid self-test log entry" },
+                    {
+                        "type": { "value": 2, "string": "Extended offline" },
+                        "status": { "value": 0, "string": "Completed without error" },
+                        "lifetime_hours": 200
+                    }
+                ]
+            }
+        });
+
+        let entries: Vec<_> = parse_json_self_test_log(&self_test_log).unwrap().collect();
+
+        assert_eq!(entries.len(), 3);
+        assert!(entries[0].is_ok());
+        assert!(entries[1].is_err());
+        assert!(entries[2].is_ok());
+
+        // Iteration must continue past the malformed row rather than
+        // stopping, and each surviving entry must still hold its own data.
+        let lifetime_hours = |entry: &SelfTestLogEntry| match entry {
+            SelfTestLogEntry::Ata(entry) => entry.lifetime_hours,
+            SelfTestLogEntry::Nvme(_) => panic!("expected an ATA self-test log entry"),
+        };
+        assert_eq!(lifetime_hours(entries[0].as_ref().unwrap()), 100);
+        assert_eq!(lifetime_hours(entries[2].as_ref().unwrap()), 200);
+    }
 }